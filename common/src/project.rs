@@ -1,3 +1,4 @@
+use idna::{domain_to_ascii, domain_to_unicode};
 use once_cell::sync::OnceCell;
 use rustrict::{Censor, Type};
 use serde::de::Error as DeError;
@@ -11,64 +12,235 @@ use std::str::FromStr;
 /// Initially we'll implement a strict subset of the IETF RFC 1123.
 /// Additionaly, while host segments are technically case-insensitive, the filesystem isn't,
 /// so we restrict project names to be lower case. We also restrict the use of profanity,
-/// as well as a list of reserved words.
+/// as well as a list of reserved words. Because a project name is also used as a path
+/// component, names that are dangerous on some filesystems (Windows device names, `.`, `..`
+/// and dot/dash-only names) are rejected as well, even if they would otherwise pass the
+/// RFC 1123 rules above.
 #[derive(Clone, Serialize, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "backend", derive(sqlx::Type, Hash))]
 #[cfg_attr(feature = "backend", sqlx(transparent))]
 pub struct ProjectName(String);
 
 impl ProjectName {
-    pub fn new(name: &str) -> Result<Self, InvalidProjectName> {
-        if Self::is_valid(name) {
-            Ok(Self(name.to_owned()))
-        } else {
-            Err(InvalidProjectName)
-        }
+    pub fn new(name: &str) -> Result<Self, ProjectNameError> {
+        Self::new_with_policy(name, &ProjectNamePolicy::default())
+    }
+
+    pub fn new_with_policy(
+        name: &str,
+        policy: &ProjectNamePolicy,
+    ) -> Result<Self, ProjectNameError> {
+        Self::validate_with_policy(name, policy)?;
+
+        Ok(Self(name.to_owned()))
     }
 
     pub fn is_valid(name: &str) -> bool {
-        fn is_valid_char(byte: u8) -> bool {
-            matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'-')
+        Self::validate(name).is_ok()
+    }
+
+    /// Clean up `name` into a canonical form and validate that: trim surrounding ASCII
+    /// whitespace, lowercase ASCII letters, and collapse runs of internal whitespace or
+    /// underscores into a single dash. This gives a forgiving "did you mean" path for names
+    /// like `" MyAssets "`, while [`Self::new`] and [`FromStr`](std::str::FromStr) stay strict
+    /// for storage invariants.
+    pub fn normalize(name: &str) -> Result<Self, ProjectNameError> {
+        let trimmed = name.trim_matches(|c: char| c.is_ascii_whitespace());
+
+        let mut normalized = String::with_capacity(trimmed.len());
+        let mut last_was_separator = false;
+        for c in trimmed.chars() {
+            if c.is_ascii_whitespace() || c == '_' {
+                if !last_was_separator && !normalized.is_empty() {
+                    normalized.push('-');
+                }
+                last_was_separator = true;
+            } else {
+                normalized.push(c.to_ascii_lowercase());
+                last_was_separator = false;
+            }
         }
 
-        fn is_profanity_free(name: &str) -> bool {
-            let (_censored, analysis) = Censor::from_str(name).censor_and_analyze();
-            !analysis.is(Type::MODERATE_OR_HIGHER)
+        // Leading separators are skipped above, but a trailing whitespace or underscore still
+        // leaves a dash, which would then be rejected as a trailing dash; trim dashes off both
+        // ends instead of surfacing that as an error. This also strips any dashes the input
+        // itself had at either end.
+        let normalized = normalized.trim_matches('-');
+
+        Self::new(normalized)
+    }
+
+    /// Run every project name rule against `name` under the default [`ProjectNamePolicy`],
+    /// returning the first one it breaks.
+    pub fn validate(name: &str) -> Result<(), ProjectNameError> {
+        Self::validate_with_policy(name, &ProjectNamePolicy::default())
+    }
+
+    /// Like [`Self::validate`], but checking the reserved words and profanity threshold of
+    /// `policy` instead of the defaults.
+    pub fn validate_with_policy(
+        name: &str,
+        policy: &ProjectNamePolicy,
+    ) -> Result<(), ProjectNameError> {
+        fn is_valid_char(ch: char) -> bool {
+            matches!(ch, 'a'..='z' | '0'..='9' | '-')
         }
 
-        fn is_reserved(name: &str) -> bool {
+        // Windows reserves these device names regardless of case or extension, so a project
+        // name equal to one of them would be unusable as a path component on that platform.
+        fn is_reserved_device_name(name: &str) -> bool {
             static INSTANCE: OnceCell<HashSet<&str>> = OnceCell::new();
             INSTANCE.get_or_init(|| {
-                HashSet::from(["shuttleapp", "shuttle", "console", "unstable", "staging"])
+                HashSet::from([
+                    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6",
+                    "com7", "com8", "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7",
+                    "lpt8", "lpt9",
+                ])
+            });
+
+            // `name` is already lowercase by the time it reaches this check.
+            INSTANCE.get().expect("Reserved words not set").contains(name)
+        }
+
+        if name.is_empty() {
+            return Err(ProjectNameError::Empty);
+        }
+
+        if name.len() >= 64 {
+            return Err(ProjectNameError::TooLong { len: name.len() });
+        }
+
+        if name == "." || name == ".." {
+            return Err(ProjectNameError::CurrentOrParentDir);
+        }
+
+        if name.chars().all(|c| c == '.' || c == '-') {
+            return Err(ProjectNameError::DotsOrDashesOnly);
+        }
+
+        if name.starts_with('-') {
+            return Err(ProjectNameError::LeadingDash);
+        }
+
+        if name.ends_with('-') {
+            return Err(ProjectNameError::TrailingDash);
+        }
+
+        if let Some((index, ch)) = name.char_indices().find(|(_, c)| !is_valid_char(*c)) {
+            return Err(ProjectNameError::InvalidChar { ch, index });
+        }
+
+        if is_reserved_device_name(name) {
+            return Err(ProjectNameError::ReservedDeviceName {
+                name: name.to_owned(),
             });
+        }
 
-            INSTANCE
-                .get()
-                .expect("Reserved words not set")
-                .contains(name)
+        if policy.reserved.contains(name) {
+            return Err(ProjectNameError::Reserved {
+                word: name.to_owned(),
+            });
         }
 
-        !name.is_empty()
-            && name.len() < 64
-            && !name.starts_with('-')
-            && !name.ends_with('-')
-            && !is_reserved(name)
-            && name.bytes().all(is_valid_char)
-            && is_profanity_free(name)
+        if let Some(threshold) = policy.profanity_threshold {
+            let (_censored, analysis) = Censor::from_str(name).censor_and_analyze();
+            if analysis.is(threshold) {
+                return Err(ProjectNameError::Profane);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accept a project name written in any script by running [IDNA/UTS-46](https://www.unicode.org/reports/tr46/)
+    /// normalization and encoding it to its ASCII-compatible (Punycode, `xn--`) form, which is
+    /// then validated like any other name. The ASCII form is what gets stored, so filesystem
+    /// and host usage stay byte-stable; use [`Self::display_unicode`] to show the name back to
+    /// a user.
+    pub fn from_unicode(name: &str) -> Result<Self, ProjectNameError> {
+        let ascii = domain_to_ascii(name).map_err(|_| ProjectNameError::InvalidUnicode)?;
+
+        Self::new(&ascii)
+    }
+
+    /// Decode the stored ASCII/Punycode form back to its Unicode representation, for display
+    /// purposes only. Returns a lazily-formatted value, mirroring [`std::path::Path::display`].
+    pub fn display_unicode(&self) -> DisplayUnicode<'_> {
+        DisplayUnicode(self)
+    }
+
+    pub fn to_unicode(&self) -> String {
+        self.display_unicode().to_string()
+    }
+}
+
+/// Formats a [`ProjectName`]'s Unicode representation. See [`ProjectName::display_unicode`].
+pub struct DisplayUnicode<'a>(&'a ProjectName);
+
+impl std::fmt::Display for DisplayUnicode<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (unicode, _) = domain_to_unicode(&self.0 .0);
+        f.write_str(&unicode)
     }
 }
 
+/// The set of rules an operator can tune when validating a [`ProjectName`]: which words are
+/// reserved, and how aggressively profanity is filtered. [`Default`] reproduces the behavior
+/// shuttle.rs itself uses.
+#[derive(Clone, Debug)]
+pub struct ProjectNamePolicy {
+    /// Names that may not be used for a project, e.g. because they collide with a subdomain
+    /// the operator's own services are hosted on.
+    pub reserved: HashSet<String>,
+    /// The `rustrict` severity at and above which a name is considered profane. `None`
+    /// disables the profanity check entirely.
+    pub profanity_threshold: Option<Type>,
+}
+
+impl Default for ProjectNamePolicy {
+    fn default() -> Self {
+        Self {
+            reserved: ["shuttleapp", "shuttle", "console", "unstable", "staging"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            profanity_threshold: Some(Type::MODERATE_OR_HIGHER),
+        }
+    }
+}
+
+/// The rule a candidate project name broke, as determined by [`ProjectName::validate`].
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
-#[error(
-    "Invalid project name. Project names must:
-    1. only contain lowercase alphanumeric characters or dashes `-`.
-    2. not start or end with a dash.
-    3. not be empty.
-    4. be shorter than 64 characters.
-    5. not contain any profanities.
-    6. not be a reserved word."
-)]
-pub struct InvalidProjectName;
+pub enum ProjectNameError {
+    #[error("project name cannot be empty")]
+    Empty,
+    #[error("project name must be shorter than 64 bytes, but is {len}")]
+    TooLong { len: usize },
+    #[error("project name cannot be `.` or `..`")]
+    CurrentOrParentDir,
+    #[error("project name cannot consist solely of dots and dashes")]
+    DotsOrDashesOnly,
+    #[error("project name cannot start with a dash")]
+    LeadingDash,
+    #[error("project name cannot end with a dash")]
+    TrailingDash,
+    #[error(
+        "project name contains the invalid character '{ch}' at position {index}; only lowercase alphanumeric characters and dashes (`-`) are allowed"
+    )]
+    InvalidChar {
+        ch: char,
+        /// Byte offset of `ch` into the name, not a character index.
+        index: usize,
+    },
+    #[error("'{name}' is a reserved device name on Windows and cannot be used as a project name")]
+    ReservedDeviceName { name: String },
+    #[error("'{word}' is a reserved word and cannot be used as a project name")]
+    Reserved { word: String },
+    #[error("project name contains profanity")]
+    Profane,
+    #[error("project name is not a valid internationalized domain name label")]
+    InvalidUnicode,
+}
 
 impl std::ops::Deref for ProjectName {
     type Target = String;
@@ -95,7 +267,7 @@ impl<'de> Deserialize<'de> for ProjectName {
 }
 
 impl FromStr for ProjectName {
-    type Err = InvalidProjectName;
+    type Err = ProjectNameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         ProjectName::new(s)
@@ -157,4 +329,120 @@ pub mod tests {
             assert!(!ProjectName::is_valid(name));
         }
     }
+
+    #[test]
+    fn validation_errors_are_specific() {
+        assert_eq!(ProjectName::validate(""), Err(ProjectNameError::Empty));
+        assert_eq!(
+            ProjectName::validate(&"a".repeat(64)),
+            Err(ProjectNameError::TooLong { len: 64 })
+        );
+        assert_eq!(
+            ProjectName::validate("-leading"),
+            Err(ProjectNameError::LeadingDash)
+        );
+        assert_eq!(
+            ProjectName::validate("trailing-"),
+            Err(ProjectNameError::TrailingDash)
+        );
+        assert_eq!(
+            ProjectName::validate("invalid.name"),
+            Err(ProjectNameError::InvalidChar { ch: '.', index: 7 })
+        );
+        assert_eq!(
+            ProjectName::validate("shuttle"),
+            Err(ProjectNameError::Reserved {
+                word: "shuttle".to_string()
+            })
+        );
+        // The reported character must be the real (non-ASCII) char, not a mojibake byte.
+        assert_eq!(
+            ProjectName::validate("münchen"),
+            Err(ProjectNameError::InvalidChar { ch: 'ü', index: 1 })
+        );
+    }
+
+    #[test]
+    fn path_dangerous_names_are_rejected() {
+        for name in ["con", "prn", "aux", "nul", "com1", "lpt9"] {
+            assert!(!ProjectName::is_valid(name));
+        }
+
+        assert_eq!(ProjectName::validate("."), Err(ProjectNameError::CurrentOrParentDir));
+        assert_eq!(ProjectName::validate(".."), Err(ProjectNameError::CurrentOrParentDir));
+        assert_eq!(
+            ProjectName::validate("---"),
+            Err(ProjectNameError::DotsOrDashesOnly)
+        );
+    }
+
+    #[test]
+    fn custom_policy_can_add_reserved_words_and_disable_profanity() {
+        let policy = ProjectNamePolicy {
+            reserved: HashSet::from(["admin".to_string()]),
+            profanity_threshold: None,
+        };
+
+        assert_eq!(
+            ProjectName::validate_with_policy("admin", &policy),
+            Err(ProjectNameError::Reserved {
+                word: "admin".to_string()
+            })
+        );
+        // Not in the custom reserved set, unlike the default policy.
+        assert!(ProjectName::validate_with_policy("shuttle", &policy).is_ok());
+        // Profanity checking is disabled by this policy.
+        assert!(ProjectName::validate_with_policy("test-condom-condom", &policy).is_ok());
+    }
+
+    #[test]
+    fn normalize_cleans_up_near_miss_names() {
+        assert_eq!(
+            ProjectName::normalize(" Dachterrasse ").unwrap(),
+            ProjectName::new("dachterrasse").unwrap()
+        );
+        assert_eq!(
+            ProjectName::normalize("my_cool_project").unwrap(),
+            ProjectName::new("my-cool-project").unwrap()
+        );
+        assert_eq!(
+            ProjectName::normalize("kebab-case").unwrap(),
+            ProjectName::new("kebab-case").unwrap()
+        );
+
+        // Still enforces the usual rules on the canonical form.
+        assert_eq!(ProjectName::normalize(""), Err(ProjectNameError::Empty));
+    }
+
+    #[test]
+    fn normalize_trims_boundary_separators_instead_of_rejecting_them() {
+        assert_eq!(
+            ProjectName::normalize("foo_").unwrap(),
+            ProjectName::new("foo").unwrap()
+        );
+        assert_eq!(
+            ProjectName::normalize("_foo_").unwrap(),
+            ProjectName::new("foo").unwrap()
+        );
+        assert_eq!(
+            ProjectName::normalize("  foo  ").unwrap(),
+            ProjectName::new("foo").unwrap()
+        );
+    }
+
+    #[test]
+    fn from_unicode_round_trips_through_punycode() {
+        let name = ProjectName::from_unicode("münchen").unwrap();
+
+        assert!(ProjectName::is_valid(&name));
+        assert!(name.starts_with("xn--"));
+        assert_eq!(name.to_unicode(), "münchen");
+    }
+
+    #[test]
+    fn from_unicode_rejects_names_too_long_once_encoded() {
+        let name = "a".repeat(63) + "ü";
+
+        assert!(ProjectName::from_unicode(&name).is_err());
+    }
 }